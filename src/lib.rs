@@ -1,15 +1,15 @@
 use std::error;
 use std::io::{BufRead, Write};
-
-pub struct Config {}
+use std::str::FromStr;
 
 extern crate chrono;
+extern crate clap;
+extern crate csv;
 extern crate rust_decimal;
 extern crate serde_json;
 
 use std::cmp::max;
 use std::collections::{HashMap, BTreeMap};
-use std::convert::TryInto;
 use std::fmt;
 
 use rust_decimal::{Decimal, RoundingStrategy};
@@ -17,17 +17,20 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use chrono::prelude::*;
+use chrono::Duration;
 
 const WEEKDAYS: usize = 7;
+const WEEKDAY_NAMES: [&str; WEEKDAYS] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
 
 #[derive(Debug)]
 enum SubError {
     //IoError(std::io::Error),
     JsonError(serde_json::Error),
     ChronoParseError(chrono::ParseError),
+    ClapError(clap::Error),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ErrorKind {
     Unknown,
     NoProjectsDefinedInConfig,
@@ -35,6 +38,17 @@ pub enum ErrorKind {
     IntervalWithMoreThanOneProject,
     FailedToParseConfig,
     FailedToParseInclusions,
+    FailedToParseArgs,
+}
+
+// A single timewarrior interval that couldn't be attributed to exactly one
+// project. Collected by `run` instead of aborting the whole report, unless
+// `Config::strict` is set.
+#[derive(Debug)]
+pub struct SkippedInterval {
+    pub id: i32,
+    pub tags: Vec<String>,
+    pub reason: ErrorKind,
 }
 
 #[derive(Debug)]
@@ -79,6 +93,9 @@ pub struct Interval {
     project: String,
     total_seconds: i64,
     weekday: u32,
+    date: NaiveDate,
+    iso_year: i32,
+    iso_week: u32,
     inclusion: Option<Inclusion>,
 }
 
@@ -88,6 +105,9 @@ impl Interval {
             project: "".to_string(),
             total_seconds: 0,
             weekday: 0,
+            date: NaiveDate::from_ymd_opt(1970, 1, 1).unwrap(),
+            iso_year: 0,
+            iso_week: 0,
             inclusion: None,
         }
     }
@@ -103,6 +123,18 @@ impl Interval {
     pub fn weekday(&self) -> u32 {
         self.weekday
     }
+
+    pub fn date(&self) -> NaiveDate {
+        self.date
+    }
+
+    pub fn iso_year(&self) -> i32 {
+        self.iso_year
+    }
+
+    pub fn iso_week(&self) -> u32 {
+        self.iso_week
+    }
 }
 
 pub struct IntervalFactory {
@@ -151,7 +183,12 @@ impl IntervalFactory {
         Ok(start.with_timezone(&Local))
     }
 
-    pub fn new_interval(&self, raw_json: &str) -> Result<Interval, Error> {
+    // Returns the outer `Err` only for hard failures (bad JSON, unparseable
+    // dates, missing project config). A tag mismatch on an otherwise
+    // well-formed interval is reported as an inner `Err(SkippedInterval)` so
+    // the caller can decide whether to collect it or abort, per
+    // `Config::strict`.
+    pub fn new_interval(&self, raw_json: &str) -> Result<Result<Interval, SkippedInterval>, Error> {
         let valid_projects = match &self.valid_projects {
             Some(projects) => {
                 if projects.len() == 0 {
@@ -169,64 +206,73 @@ impl IntervalFactory {
         };
 
         let mut project: &str = "";
+        let mut reason: Option<ErrorKind> = None;
         for tag in &inclusion.tags {
             if valid_projects.contains(&tag) {
                 if project == "" {
                     project = tag;
                 } else {
-                    return Err(Error{kind: ErrorKind::IntervalWithMoreThanOneProject, ..Default::default()});
+                    reason = Some(ErrorKind::IntervalWithMoreThanOneProject);
+                    break;
                 }
             }
         }
 
-        if project == "" {
-            return Err(Error{kind: ErrorKind::IntervalWithNoProjects, ..Default::default()});
+        if reason.is_none() && project == "" {
+            reason = Some(ErrorKind::IntervalWithNoProjects);
+        }
+
+        if let Some(reason) = reason {
+            return Ok(Err(SkippedInterval {
+                id: inclusion.id,
+                tags: inclusion.tags,
+                reason: reason,
+            }));
         }
 
         let start = IntervalFactory::string_to_datetime(&inclusion.start)?;
         let end = IntervalFactory::string_to_datetime(&inclusion.end)?;
         let total_seconds = end.signed_duration_since(start).num_seconds();
+        let iso_week = start.iso_week();
 
-        Ok(Interval {
+        Ok(Ok(Interval {
             project: project.to_string(),
             total_seconds: total_seconds,
             weekday: start.weekday().num_days_from_monday(),
+            date: start.date_naive(),
+            iso_year: iso_week.year(),
+            iso_week: iso_week.week(),
             inclusion: Some(inclusion),
-        })
+        }))
     }
 }
 
 type RowT = Vec<Decimal>;
+// The calendar date `week_start` falls on for a given week, e.g. the Sunday
+// a Sun-Sat week begins on. Bucketing on this (rather than the ISO year/week
+// number, which is always Monday-anchored) keeps a single user-week together
+// regardless of `week_start`.
+type WeekKey = NaiveDate;
 
+// One project/hours matrix, for a single `week_start`-anchored week.
 #[derive(Debug)]
-pub struct Report {
+struct WeekTable {
     data: BTreeMap<String, RowT>,
     totals: RowT,
-    column_width: usize,
     tag_width: usize,
 }
 
-impl Report {
-    pub fn from_intervals(_options: &HashMap<String, String>, intervals: &Vec<Interval>) -> Report {
-        // Sum up the intervals into total seconds per project / per day
-        let mut raw_data: BTreeMap<&str, Vec<i64>> = BTreeMap::new();
-        for interval in intervals {
-            let project_data = raw_data
-                .entry(&interval.project)
-                .or_insert(vec![0; WEEKDAYS]);
-
-            let weekday: usize = interval.weekday().try_into().unwrap();
-            project_data[weekday] += interval.total_seconds();
-        }
-
+impl WeekTable {
+    fn from_raw(raw: &BTreeMap<&str, Vec<i64>>, rounding: Rounding, rounding_strategy: RoundingStrategy) -> WeekTable {
         let seconds_per_hour = Decimal::new(3600, 0);
 
         let mut data: BTreeMap<String, RowT> = BTreeMap::new();
         let mut totals: RowT = vec![Decimal::new(0, 0); WEEKDAYS + 1];
-        // Convert the raw seconds into hours and 10ths of hours, and sum up the
-        // totals
+        // Convert the raw seconds into hours, rounded to the configured
+        // billing increment, and sum up the totals from the rounded cells
+        // so columns still add up.
         let mut tag_width: usize = 0;
-        for (key, value) in &raw_data {
+        for (key, value) in raw {
             tag_width = max(tag_width, key.len());
             let project_data = data
                 .entry(String::from(*key))
@@ -234,8 +280,8 @@ impl Report {
 
             let mut project_total = Decimal::new(0, 0);
             for weekday in 0..value.len() {
-                project_data[weekday] =
-                    (Decimal::new(value[weekday], 0) / seconds_per_hour).round_dp_with_strategy(1, RoundingStrategy::RoundHalfUp);
+                let hours = Decimal::new(value[weekday], 0) / seconds_per_hour;
+                project_data[weekday] = rounding.round(hours, rounding_strategy);
                 project_total += project_data[weekday];
                 totals[weekday] += project_data[weekday];
             }
@@ -249,56 +295,639 @@ impl Report {
         totals[WEEKDAYS] = total;
         tag_width = max(tag_width, "totals".len());
 
-        Report {
+        WeekTable {
             data: data,
             totals: totals,
+            tag_width: tag_width,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Report {
+    weeks: BTreeMap<WeekKey, WeekTable>,
+    grand_totals: RowT,
+    column_width: usize,
+    tag_width: usize,
+    show_summary: bool,
+    skipped: Vec<SkippedInterval>,
+    day_names: [&'static str; WEEKDAYS],
+}
+
+// Column index for a day, relative to `week_start` instead of always Monday.
+fn weekday_column(weekday: u32, week_start: Weekday) -> usize {
+    let offset = week_start.num_days_from_monday();
+    ((weekday + WEEKDAYS as u32 - offset) % WEEKDAYS as u32) as usize
+}
+
+fn weekday_names(week_start: Weekday) -> [&'static str; WEEKDAYS] {
+    let offset = week_start.num_days_from_monday() as usize;
+    let mut names = WEEKDAY_NAMES;
+    for i in 0..WEEKDAYS {
+        names[i] = WEEKDAY_NAMES[(i + offset) % WEEKDAYS];
+    }
+    names
+}
+
+// The `week_start`-anchored week key that `date` belongs to: the most
+// recent occurrence of `week_start` on or before `date`.
+fn week_start_date(date: NaiveDate, week_start: Weekday) -> NaiveDate {
+    let offset = (date.weekday().num_days_from_monday() + WEEKDAYS as u32 - week_start.num_days_from_monday()) % WEEKDAYS as u32;
+    date - Duration::days(offset as i64)
+}
+
+// A human-readable label for the week beginning on `week_start`, e.g.
+// "2024-12-29 to 2025-01-04".
+fn week_label(week_start: NaiveDate) -> String {
+    let week_end = week_start + Duration::days(WEEKDAYS as i64 - 1);
+    format!("{} to {}", week_start.format("%Y-%m-%d"), week_end.format("%Y-%m-%d"))
+}
+
+// Per-project share of tracked time and average hours on the days it had
+// any activity, relative to `totals`. Also used to summarize the totals
+// row itself, where it works out to (100%, average across active days).
+fn project_summary(project_total: Decimal, active_days: usize, grand_total: Decimal) -> (Decimal, Decimal) {
+    let zero = Decimal::new(0, 0);
+    let percentage = if grand_total == zero {
+        zero
+    } else {
+        (project_total / grand_total * Decimal::new(100, 0)).round_dp_with_strategy(1, RoundingStrategy::RoundHalfUp)
+    };
+    let average = if active_days == 0 {
+        zero
+    } else {
+        (project_total / Decimal::new(active_days as i64, 0)).round_dp_with_strategy(1, RoundingStrategy::RoundHalfUp)
+    };
+    (percentage, average)
+}
+
+fn row_summary(row: &RowT, grand_total: Decimal) -> (Decimal, Decimal) {
+    let zero = Decimal::new(0, 0);
+    let active_days = row[0..WEEKDAYS].iter().filter(|val| **val != zero).count();
+    project_summary(row[WEEKDAYS], active_days, grand_total)
+}
+
+impl Report {
+    pub fn from_intervals(_options: &HashMap<String, String>, intervals: &Vec<Interval>, rounding: Rounding, rounding_strategy: RoundingStrategy, show_summary: bool, skipped: Vec<SkippedInterval>, week_start: Weekday) -> Report {
+        // Sum up the intervals into total seconds per week / project / day,
+        // bucketing weeks by `week_start` rather than the ISO week number so
+        // a single user-week isn't split across two tables.
+        let mut raw_data: BTreeMap<WeekKey, BTreeMap<&str, Vec<i64>>> = BTreeMap::new();
+        for interval in intervals {
+            let week_data = raw_data
+                .entry(week_start_date(interval.date(), week_start))
+                .or_insert_with(BTreeMap::new);
+            let project_data = week_data
+                .entry(&interval.project)
+                .or_insert(vec![0; WEEKDAYS]);
+
+            let weekday = weekday_column(interval.weekday(), week_start);
+            project_data[weekday] += interval.total_seconds();
+        }
+
+        let mut weeks: BTreeMap<WeekKey, WeekTable> = BTreeMap::new();
+        let mut grand_totals: RowT = vec![Decimal::new(0, 0); WEEKDAYS + 1];
+        let mut tag_width: usize = "totals".len();
+        for (key, raw) in &raw_data {
+            let table = WeekTable::from_raw(raw, rounding, rounding_strategy);
+            tag_width = max(tag_width, table.tag_width);
+            for weekday in 0..WEEKDAYS + 1 {
+                grand_totals[weekday] += table.totals[weekday];
+            }
+            weeks.insert(*key, table);
+        }
+
+        Report {
+            weeks: weeks,
+            grand_totals: grand_totals,
             column_width: 6,
             tag_width: tag_width,
+            show_summary: show_summary,
+            skipped: skipped,
+            day_names: weekday_names(week_start),
         }
     }
 }
 
+fn write_row(f: &mut fmt::Formatter, tag: &str, tag_width: usize, column_width: usize, data: &RowT, summary: Option<(Decimal, Decimal)>) -> fmt::Result {
+    write!(f, "{:<0width$} |", tag, width = tag_width)?;
+    let zero = Decimal::new(0, 0);
+    for val in data {
+        if val == &zero {
+            write!(f, " {:>width$} |", " ", width = column_width)?;
+        } else {
+            write!(f, " {:>width$} |", val, width = column_width)?;
+        }
+    }
+    if let Some((percentage, average)) = summary {
+        write!(f, " {:>width$} |", format!("{}%", percentage), width = column_width)?;
+        write!(f, " {:>width$} |", average, width = column_width)?;
+    }
+    write!(f, "\n")
+}
+
+fn write_table(f: &mut fmt::Formatter, tag_width: usize, column_width: usize, day_names: &[&str; WEEKDAYS], data: &BTreeMap<String, RowT>, totals: &RowT, show_summary: bool) -> fmt::Result {
+    let summary_columns = if show_summary { 2 } else { 0 };
+    let separator = format!(
+        "{}=|{}",
+        "=".repeat(tag_width),
+        format!("={}=|", "=".repeat(column_width)).repeat(WEEKDAYS + 1 + summary_columns)
+    );
+
+    write!(f, "{} | ", " ".repeat(tag_width))?;
+    for day in day_names.iter().chain(["Tot"].iter()) {
+        write!(f, "{:>0width$} | ", day, width = column_width)?;
+    }
+    if show_summary {
+        write!(f, "{:>0width$} | ", "%", width = column_width)?;
+        write!(f, "{:>0width$} | ", "Avg", width = column_width)?;
+    }
+    write!(f, "\n{}\n", separator)?;
+
+    let grand_total = totals[WEEKDAYS];
+    for (key, value) in data {
+        let summary = if show_summary { Some(row_summary(value, grand_total)) } else { None };
+        write_row(f, key, tag_width, column_width, value, summary)?;
+    }
+
+    write!(f, "{}\n", separator)?;
+
+    let totals_summary = if show_summary { Some(row_summary(totals, grand_total)) } else { None };
+    write_row(f, "totals", tag_width, column_width, totals, totals_summary)
+}
+
+fn write_skipped(f: &mut fmt::Formatter, skipped: &Vec<SkippedInterval>) -> fmt::Result {
+    if skipped.is_empty() {
+        return Ok(());
+    }
+
+    write!(f, "\nSkipped intervals\n")?;
+    for interval in skipped {
+        write!(f, "  #{}: tags={:?}, reason={:?}\n", interval.id, interval.tags, interval.reason)?;
+    }
+    Ok(())
+}
+
 impl fmt::Display for Report {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        fn write_project(report: &Report, f: &mut fmt::Formatter, project: &str, data: &RowT) -> fmt::Result {
-            write!(f, "{:<0width$} |", project, width = report.tag_width)?;
-            let zero = Decimal::new(0, 0);
-            for val in data {
-                if val == &zero {
-                    write!(f, " {:>width$} |", " ", width = report.column_width)?;
-                } else {
-                    write!(f, " {:>width$} |", val, width =report.column_width)?;
+        // A report spanning a single week renders as one plain table, the
+        // same as before per-week bucketing existed.
+        if self.weeks.len() <= 1 {
+            match self.weeks.values().next() {
+                Some(table) => write_table(f, self.tag_width, self.column_width, &self.day_names, &table.data, &table.totals, self.show_summary)?,
+                None => {
+                    let empty = BTreeMap::new();
+                    write_table(f, self.tag_width, self.column_width, &self.day_names, &empty, &self.grand_totals, self.show_summary)?
                 }
+            };
+        } else {
+            for (week_start, table) in &self.weeks {
+                write!(f, "Week {}\n", week_label(*week_start))?;
+                write_table(f, self.tag_width, self.column_width, &self.day_names, &table.data, &table.totals, self.show_summary)?;
+                write!(f, "\n")?;
             }
-            write!(f, "\n")
+
+            write!(f, "Grand Total\n")?;
+            let empty = BTreeMap::new();
+            write_table(f, self.tag_width, self.column_width, &self.day_names, &empty, &self.grand_totals, self.show_summary)?;
         }
 
-        let separator = format!(
-            "{}=|{}",
-            "=".repeat(self.tag_width),
-            format!("={}=|", "=".repeat(self.column_width)).repeat(WEEKDAYS + 1)
-        );
+        write_skipped(f, &self.skipped)
+    }
+}
 
-        write!(f, "{} | ", " ".repeat(self.tag_width))?;
-        for day in ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun", "Tot"].iter() {
-            write!(f, "{:>0width$} | ", day, width = self.column_width)?;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Csv,
+    Json,
+    Markdown,
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(Format::Text),
+            "csv" => Ok(Format::Csv),
+            "json" => Ok(Format::Json),
+            "markdown" | "md" => Ok(Format::Markdown),
+            _ => Err(Error{kind: ErrorKind::FailedToParseArgs, ..Default::default()}),
         }
-        write!(f, "\n{}\n", separator)?;
+    }
+}
 
-        for (key, value) in &self.data {
-            write_project(self, f, &key, &value)?;
+// Billing increment the reported hours are rounded to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rounding {
+    Tenth,
+    QuarterHour,
+    // Keeps two decimal places; no increment is applied.
+    Exact,
+}
+
+impl Rounding {
+    // Rounds `hours` to this increment using `strategy` for the tie-break.
+    fn round(&self, hours: Decimal, strategy: RoundingStrategy) -> Decimal {
+        match self {
+            Rounding::Tenth => hours.round_dp_with_strategy(1, strategy),
+            Rounding::QuarterHour => {
+                let increment = Decimal::new(25, 2);
+                (hours / increment).round_dp_with_strategy(0, strategy) * increment
+            }
+            Rounding::Exact => hours.round_dp_with_strategy(2, strategy),
+        }
+    }
+}
+
+impl FromStr for Rounding {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "tenth" => Ok(Rounding::Tenth),
+            "quarter" | "quarter-hour" | "quarterhour" => Ok(Rounding::QuarterHour),
+            "exact" => Ok(Rounding::Exact),
+            _ => Err(Error{kind: ErrorKind::FailedToParseArgs, ..Default::default()}),
+        }
+    }
+}
+
+fn rounding_strategy_from_str(s: &str) -> Result<RoundingStrategy, Error> {
+    match s.to_lowercase().as_str() {
+        "half-up" => Ok(RoundingStrategy::RoundHalfUp),
+        "half-down" => Ok(RoundingStrategy::RoundHalfDown),
+        "half-even" => Ok(RoundingStrategy::MidpointNearestEven),
+        _ => Err(Error{kind: ErrorKind::FailedToParseArgs, ..Default::default()}),
+    }
+}
+
+fn weekday_from_str(s: &str) -> Result<Weekday, Error> {
+    match s.to_lowercase().as_str() {
+        "monday" | "mon" => Ok(Weekday::Mon),
+        "tuesday" | "tue" => Ok(Weekday::Tue),
+        "wednesday" | "wed" => Ok(Weekday::Wed),
+        "thursday" | "thu" => Ok(Weekday::Thu),
+        "friday" | "fri" => Ok(Weekday::Fri),
+        "saturday" | "sat" => Ok(Weekday::Sat),
+        "sunday" | "sun" => Ok(Weekday::Sun),
+        _ => Err(Error{kind: ErrorKind::FailedToParseArgs, ..Default::default()}),
+    }
+}
+
+pub struct Config {
+    pub format: Format,
+    pub week_start: Weekday,
+    pub rounding: Rounding,
+    pub rounding_strategy: RoundingStrategy,
+    pub project_override: Option<String>,
+    pub summary: bool,
+    // When set, an interval that can't be attributed to exactly one project
+    // aborts the whole report (the original behavior). Otherwise such
+    // intervals are skipped and reported in a diagnostics section.
+    pub strict: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            format: Format::Text,
+            week_start: Weekday::Mon,
+            rounding: Rounding::Tenth,
+            rounding_strategy: RoundingStrategy::RoundHalfUp,
+            project_override: None,
+            summary: false,
+            strict: false,
         }
+    }
+}
+
+impl Config {
+    pub fn from_args<I, T>(args: I) -> Result<Config, Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<std::ffi::OsString> + Clone,
+    {
+        let matches = clap::Command::new("timewarrior-timesheet-report")
+            .arg(clap::Arg::new("format").long("format").value_name("FORMAT").default_value("text"))
+            .arg(clap::Arg::new("week-start").long("week-start").value_name("DAY").default_value("monday"))
+            .arg(clap::Arg::new("rounding").long("rounding").value_name("INCREMENT").default_value("tenth"))
+            .arg(clap::Arg::new("rounding-strategy").long("rounding-strategy").value_name("STRATEGY").default_value("half-up"))
+            .arg(clap::Arg::new("project").long("project").value_name("PROJECT"))
+            .arg(clap::Arg::new("summary").long("summary").action(clap::ArgAction::SetTrue))
+            .arg(clap::Arg::new("strict").long("strict").action(clap::ArgAction::SetTrue))
+            .try_get_matches_from(args)
+            .map_err(|error| Error{kind: ErrorKind::FailedToParseArgs, sub_error: Some(SubError::ClapError(error)), ..Default::default()})?;
 
-        write!(f, "{}\n", separator)?;
+        let format = matches.get_one::<String>("format").unwrap().parse()?;
+        let week_start = weekday_from_str(matches.get_one::<String>("week-start").unwrap())?;
+        let rounding = matches.get_one::<String>("rounding").unwrap().parse()?;
+        let rounding_strategy = rounding_strategy_from_str(matches.get_one::<String>("rounding-strategy").unwrap())?;
+        let project_override = matches.get_one::<String>("project").cloned();
+        let summary = matches.get_flag("summary");
+        let strict = matches.get_flag("strict");
 
-        write_project(self, f, "totals", &self.totals)
+        Ok(Config {
+            format: format,
+            week_start: week_start,
+            rounding: rounding,
+            rounding_strategy: rounding_strategy,
+            project_override: project_override,
+            summary: summary,
+            strict: strict,
+        })
+    }
+
+    // Timewarrior passes extension config through the `rc.timesheet.*`
+    // options already collected by `run`'s options loop; let those override
+    // whatever was set on the command line.
+    fn merge_options(&mut self, options: &HashMap<String, String>) {
+        if let Some(value) = options.get("timesheet.format") {
+            if let Ok(format) = value.parse() {
+                self.format = format;
+            }
+        }
+        if let Some(value) = options.get("timesheet.rounding") {
+            if let Ok(rounding) = value.parse() {
+                self.rounding = rounding;
+            }
+        }
+        if let Some(value) = options.get("timesheet.rounding_strategy") {
+            if let Ok(strategy) = rounding_strategy_from_str(value) {
+                self.rounding_strategy = strategy;
+            }
+        }
+        if let Some(value) = options.get("timesheet.week_start") {
+            if let Ok(week_start) = weekday_from_str(value) {
+                self.week_start = week_start;
+            }
+        }
+        if let Some(value) = options.get("timesheet.project") {
+            self.project_override = Some(value.clone());
+        }
+        if let Some(value) = options.get("timesheet.summary") {
+            self.summary = value == "1" || value.eq_ignore_ascii_case("true");
+        }
+        if let Some(value) = options.get("timesheet.strict") {
+            self.strict = value == "1" || value.eq_ignore_ascii_case("true");
+        }
     }
 }
 
+impl Report {
+    pub fn render(&self, format: Format, output: &mut dyn Write) -> Result<(), Box<dyn error::Error>> {
+        match format {
+            Format::Text => write!(output, "{}", self)?,
+            Format::Csv => self.render_csv(output)?,
+            Format::Json => self.render_json(output)?,
+            Format::Markdown => self.render_markdown(output)?,
+        }
+        Ok(())
+    }
+
+    fn render_csv(&self, output: &mut dyn Write) -> Result<(), Box<dyn error::Error>> {
+        // The day columns are named after `self.day_names`, which rotates
+        // with `week_start`, so the header can't come from a fixed set of
+        // struct field names. Write it by hand and serialize the data rows
+        // against it with headers disabled; `cells` holds the day values,
+        // the total, and (when enabled) the summary columns, in that order.
+        #[derive(Serialize)]
+        struct CsvRow {
+            week: String,
+            tag: String,
+            cells: Vec<String>,
+        }
+
+        #[derive(Serialize)]
+        struct SkippedCsvRow {
+            id: i32,
+            tags: String,
+            reason: String,
+        }
+
+        fn row_cells(row: &RowT, summary: Option<(Decimal, Decimal)>) -> Vec<String> {
+            let mut cells: Vec<String> = row.iter().map(|val| val.to_string()).collect();
+            if let Some((percentage, average)) = summary {
+                cells.push(percentage.to_string());
+                cells.push(average.to_string());
+            }
+            cells
+        }
+
+        // `flexible` lets the trailing "skipped intervals" section use its
+        // own, narrower set of columns; `has_headers(false)` keeps the
+        // writer from inventing its own header row from `CsvRow`'s field
+        // names on top of the one we write below.
+        let mut writer = csv::WriterBuilder::new().flexible(true).has_headers(false).from_writer(output);
+
+        let mut header: Vec<String> = vec!["week".to_string(), "project".to_string()];
+        header.extend(self.day_names.iter().map(|day| day.to_string()));
+        header.push("total".to_string());
+        if self.show_summary {
+            header.push("percentage".to_string());
+            header.push("avg_hours_per_day".to_string());
+        }
+        writer.write_record(&header)?;
+
+        for (week_start, table) in &self.weeks {
+            let label = week_label(*week_start);
+            let grand_total = table.totals[WEEKDAYS];
+            for (project, row) in &table.data {
+                let summary = if self.show_summary { Some(row_summary(row, grand_total)) } else { None };
+                writer.serialize(CsvRow { week: label.clone(), tag: project.clone(), cells: row_cells(row, summary) })?;
+            }
+
+            let totals_summary = if self.show_summary { Some(row_summary(&table.totals, grand_total)) } else { None };
+            writer.serialize(CsvRow { week: label.clone(), tag: "totals".to_string(), cells: row_cells(&table.totals, totals_summary) })?;
+        }
+
+        let grand_totals_summary = if self.show_summary {
+            Some(row_summary(&self.grand_totals, self.grand_totals[WEEKDAYS]))
+        } else {
+            None
+        };
+        writer.serialize(CsvRow { week: "all".to_string(), tag: "totals".to_string(), cells: row_cells(&self.grand_totals, grand_totals_summary) })?;
+
+        if !self.skipped.is_empty() {
+            writer.write_record(&Vec::<String>::new())?;
+            writer.write_record(&["id", "tags", "reason"])?;
+            for interval in &self.skipped {
+                writer.serialize(SkippedCsvRow {
+                    id: interval.id,
+                    tags: interval.tags.join(";"),
+                    reason: format!("{:?}", interval.reason),
+                })?;
+            }
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    fn render_json(&self, output: &mut dyn Write) -> Result<(), Box<dyn error::Error>> {
+        // `days` is keyed by `self.day_names` and flattened into the row, so
+        // the JSON keys rotate with `week_start` the same as the other
+        // formats.
+        #[derive(Serialize)]
+        struct RowJson {
+            #[serde(flatten)]
+            days: BTreeMap<String, String>,
+            total: String,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            percentage: Option<String>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            avg_hours_per_day: Option<String>,
+        }
+
+        #[derive(Serialize)]
+        struct WeekJson {
+            projects: BTreeMap<String, RowJson>,
+            totals: RowJson,
+        }
+
+        #[derive(Serialize)]
+        struct SkippedJson {
+            id: i32,
+            tags: Vec<String>,
+            reason: String,
+        }
+
+        #[derive(Serialize)]
+        struct ReportJson {
+            weeks: BTreeMap<String, WeekJson>,
+            grand_totals: RowJson,
+            skipped_intervals: Vec<SkippedJson>,
+        }
+
+        fn row_to_json(day_names: &[&str; WEEKDAYS], row: &RowT, summary: Option<(Decimal, Decimal)>) -> RowJson {
+            let mut days = BTreeMap::new();
+            for (day, val) in day_names.iter().zip(row.iter()) {
+                days.insert(day.to_string(), val.to_string());
+            }
+            RowJson {
+                days: days,
+                total: row[WEEKDAYS].to_string(),
+                percentage: summary.map(|(percentage, _)| percentage.to_string()),
+                avg_hours_per_day: summary.map(|(_, average)| average.to_string()),
+            }
+        }
+
+        let mut weeks = BTreeMap::new();
+        for (week_start, table) in &self.weeks {
+            let grand_total = table.totals[WEEKDAYS];
+            let mut projects = BTreeMap::new();
+            for (project, row) in &table.data {
+                let summary = if self.show_summary { Some(row_summary(row, grand_total)) } else { None };
+                projects.insert(project.clone(), row_to_json(&self.day_names, row, summary));
+            }
 
-pub fn run(_config: Config, input: &mut dyn BufRead, output: &mut dyn Write) -> Result<(), Box<dyn error::Error>> {
+            let totals_summary = if self.show_summary { Some(row_summary(&table.totals, grand_total)) } else { None };
+            weeks.insert(week_label(*week_start), WeekJson {
+                projects: projects,
+                totals: row_to_json(&self.day_names, &table.totals, totals_summary),
+            });
+        }
+
+        let grand_totals_summary = if self.show_summary {
+            Some(row_summary(&self.grand_totals, self.grand_totals[WEEKDAYS]))
+        } else {
+            None
+        };
+
+        let skipped_intervals = self.skipped.iter().map(|interval| SkippedJson {
+            id: interval.id,
+            tags: interval.tags.clone(),
+            reason: format!("{:?}", interval.reason),
+        }).collect();
+
+        let report = ReportJson {
+            weeks: weeks,
+            grand_totals: row_to_json(&self.day_names, &self.grand_totals, grand_totals_summary),
+            skipped_intervals: skipped_intervals,
+        };
+
+        serde_json::to_writer_pretty(output, &report)?;
+        Ok(())
+    }
+
+    fn render_markdown(&self, output: &mut dyn Write) -> Result<(), Box<dyn error::Error>> {
+        fn write_row(output: &mut dyn Write, tag: &str, row: &RowT, summary: Option<(Decimal, Decimal)>) -> Result<(), Box<dyn error::Error>> {
+            write!(output, "| {} |", tag)?;
+            for val in row {
+                write!(output, " {} |", val)?;
+            }
+            if let Some((percentage, average)) = summary {
+                write!(output, " {}% | {} |", percentage, average)?;
+            }
+            write!(output, "\n")?;
+            Ok(())
+        }
+
+        fn write_table(output: &mut dyn Write, day_names: &[&str; WEEKDAYS], data: &BTreeMap<String, RowT>, totals: &RowT, show_summary: bool) -> Result<(), Box<dyn error::Error>> {
+            write!(output, "| |")?;
+            for day in day_names.iter().chain(["Total"].iter()) {
+                write!(output, " {} |", day)?;
+            }
+            if show_summary {
+                write!(output, " % | Avg |")?;
+            }
+            write!(output, "\n|---|")?;
+            for _ in 0..WEEKDAYS + 1 {
+                write!(output, "---|")?;
+            }
+            if show_summary {
+                write!(output, "---|---|")?;
+            }
+            write!(output, "\n")?;
+
+            let grand_total = totals[WEEKDAYS];
+            for (project, row) in data {
+                let summary = if show_summary { Some(row_summary(row, grand_total)) } else { None };
+                write_row(output, project, row, summary)?;
+            }
+            let totals_summary = if show_summary { Some(row_summary(totals, grand_total)) } else { None };
+            write_row(output, "totals", totals, totals_summary)
+        }
+
+        fn write_skipped(output: &mut dyn Write, skipped: &Vec<SkippedInterval>) -> Result<(), Box<dyn error::Error>> {
+            if skipped.is_empty() {
+                return Ok(());
+            }
+
+            write!(output, "\n### Skipped intervals\n\n")?;
+            write!(output, "| id | tags | reason |\n|---|---|---|\n")?;
+            for interval in skipped {
+                write!(output, "| {} | {} | {:?} |\n", interval.id, interval.tags.join(", "), interval.reason)?;
+            }
+            Ok(())
+        }
+
+        if self.weeks.len() <= 1 {
+            match self.weeks.values().next() {
+                Some(table) => write_table(output, &self.day_names, &table.data, &table.totals, self.show_summary)?,
+                None => write_table(output, &self.day_names, &BTreeMap::new(), &self.grand_totals, self.show_summary)?,
+            };
+        } else {
+            for (week_start, table) in &self.weeks {
+                write!(output, "### Week {}\n\n", week_label(*week_start))?;
+                write_table(output, &self.day_names, &table.data, &table.totals, self.show_summary)?;
+                write!(output, "\n")?;
+            }
+
+            write!(output, "### Grand Total\n\n")?;
+            write_table(output, &self.day_names, &BTreeMap::new(), &self.grand_totals, self.show_summary)?;
+        }
+
+        write_skipped(output, &self.skipped)
+    }
+}
+
+pub fn run(mut config: Config, input: &mut dyn BufRead, output: &mut dyn Write) -> Result<(), Box<dyn error::Error>> {
     let mut options_finished = false;
     let mut intervals: Vec<Interval> = Vec::new();
+    let mut skipped: Vec<SkippedInterval> = Vec::new();
     let mut factory: IntervalFactory = IntervalFactory::new();
     let mut options: HashMap<String, String> = HashMap::new();
 
@@ -314,11 +943,24 @@ pub fn run(_config: Config, input: &mut dyn BufRead, output: &mut dyn Write) ->
                 },
                 None => return Err(Box::new(Error{kind: ErrorKind::NoProjectsDefinedInConfig, ..Default::default()})),
             }
+            config.merge_options(&options);
         } else if line != "" && line != "]" {
             if options_finished {
                 let raw_json = line.trim_matches(',');
-                let interval = factory.new_interval(&raw_json)?;
-                intervals.push(interval);
+                match factory.new_interval(&raw_json)? {
+                    Ok(mut interval) => {
+                        if let Some(project) = &config.project_override {
+                            interval.project = project.clone();
+                        }
+                        intervals.push(interval);
+                    }
+                    Err(interval) => {
+                        if config.strict {
+                            return Err(Box::new(Error{kind: interval.reason, ..Default::default()}));
+                        }
+                        skipped.push(interval);
+                    }
+                }
             } else {
                 let parts: Vec<&str> = line.splitn(2, ':').collect();
                 if parts.len() != 2 {
@@ -331,8 +973,8 @@ pub fn run(_config: Config, input: &mut dyn BufRead, output: &mut dyn Write) ->
         }
     }
 
-    let report = Report::from_intervals(&options, &intervals);
-    write!(output, "{}", report)?;
+    let report = Report::from_intervals(&options, &intervals, config.rounding, config.rounding_strategy, config.summary, skipped, config.week_start);
+    report.render(config.format, output)?;
 
     Ok(())
 }
@@ -340,7 +982,166 @@ pub fn run(_config: Config, input: &mut dyn BufRead, output: &mut dyn Write) ->
 #[cfg(test)]
 mod tests {
     use std::io;
-    // use super::*;
+    use super::*;
+
+    #[test]
+    fn rounding_strategy_from_str_parses_known_strategies() {
+        assert_eq!(rounding_strategy_from_str("half-up").unwrap(), RoundingStrategy::RoundHalfUp);
+        assert_eq!(rounding_strategy_from_str("half-down").unwrap(), RoundingStrategy::RoundHalfDown);
+        assert_eq!(rounding_strategy_from_str("half-even").unwrap(), RoundingStrategy::MidpointNearestEven);
+        assert!(rounding_strategy_from_str("nearest").is_err());
+    }
+
+    #[test]
+    fn quarter_hour_rounds_to_the_nearest_fifteen_minutes() {
+        let strategy = RoundingStrategy::RoundHalfUp;
+        assert_eq!(Rounding::QuarterHour.round(Decimal::new(13, 1), strategy), Decimal::new(125, 2));
+        assert_eq!(Rounding::QuarterHour.round(Decimal::new(138, 2), strategy), Decimal::new(150, 2));
+        assert_eq!(Rounding::QuarterHour.round(Decimal::new(0, 0), strategy), Decimal::new(0, 0));
+    }
+
+    #[test]
+    fn weekday_column_is_relative_to_week_start() {
+        assert_eq!(weekday_column(0, Weekday::Mon), 0);
+        assert_eq!(weekday_column(6, Weekday::Mon), 6);
+        assert_eq!(weekday_column(0, Weekday::Sun), 1);
+        assert_eq!(weekday_column(6, Weekday::Sun), 0);
+    }
+
+    #[test]
+    fn intervals_in_different_iso_weeks_bucket_separately() {
+        let mut factory = IntervalFactory::new();
+        factory.parse_projects(r#"["work"]"#).unwrap();
+
+        // 2024-12-30 is a Monday in ISO week 2025-W01; 2024-12-29 is a Sunday
+        // in ISO week 2024-W52.
+        let week_52 = factory.new_interval(
+            r#"{"id":1,"start":"20241229T090000Z","end":"20241229T100000Z","tags":["work"]}"#,
+        ).unwrap().unwrap();
+        let week_1 = factory.new_interval(
+            r#"{"id":2,"start":"20241230T090000Z","end":"20241230T100000Z","tags":["work"]}"#,
+        ).unwrap().unwrap();
+
+        assert_eq!((week_52.iso_year(), week_52.iso_week()), (2024, 52));
+        assert_eq!((week_1.iso_year(), week_1.iso_week()), (2025, 1));
+
+        let report = Report::from_intervals(
+            &HashMap::new(),
+            &vec![week_52, week_1],
+            Rounding::Exact,
+            RoundingStrategy::RoundHalfUp,
+            false,
+            Vec::new(),
+            Weekday::Mon,
+        );
+        assert_eq!(report.weeks.len(), 2);
+    }
+
+    #[test]
+    fn week_bucketing_follows_week_start_not_iso_week() {
+        let mut factory = IntervalFactory::new();
+        factory.parse_projects(r#"["work"]"#).unwrap();
+
+        // Sun 2024-12-29 .. Sat 2025-01-04 is one coherent Sun-Sat week, even
+        // though it straddles the Monday-anchored ISO week boundary
+        // (2024-W52 / 2025-W01).
+        let days = [
+            "20241229T090000Z",
+            "20241230T090000Z",
+            "20241231T090000Z",
+            "20250101T090000Z",
+            "20250102T090000Z",
+            "20250103T090000Z",
+            "20250104T090000Z",
+        ];
+        let intervals: Vec<Interval> = days.iter().enumerate().map(|(i, start)| {
+            let raw = format!(r#"{{"id":{},"start":"{}","end":"{}","tags":["work"]}}"#, i, start, start);
+            factory.new_interval(&raw).unwrap().unwrap()
+        }).collect();
+
+        let report = Report::from_intervals(
+            &HashMap::new(),
+            &intervals,
+            Rounding::Exact,
+            RoundingStrategy::RoundHalfUp,
+            false,
+            Vec::new(),
+            Weekday::Sun,
+        );
+        assert_eq!(report.weeks.len(), 1);
+    }
+
+    #[test]
+    fn merge_options_overrides_command_line_config() {
+        let mut config = Config::from_args(["bin", "--rounding=quarter"]).unwrap();
+        assert_eq!(config.rounding, Rounding::QuarterHour);
+
+        let mut options: HashMap<String, String> = HashMap::new();
+        options.insert("timesheet.rounding".to_string(), "exact".to_string());
+        options.insert("timesheet.week_start".to_string(), "sunday".to_string());
+        config.merge_options(&options);
+
+        assert_eq!(config.rounding, Rounding::Exact);
+        assert_eq!(config.week_start, Weekday::Sun);
+    }
+
+    #[test]
+    fn row_summary_computes_percentage_and_average_over_active_days() {
+        let zero = Decimal::new(0, 0);
+        let mut row: RowT = vec![zero; WEEKDAYS + 1];
+        row[0] = Decimal::new(20, 1); // 2.0 hours Monday
+        row[1] = Decimal::new(20, 1); // 2.0 hours Tuesday
+        row[WEEKDAYS] = row[0] + row[1];
+
+        let (percentage, average) = row_summary(&row, Decimal::new(80, 1));
+        assert_eq!(percentage, Decimal::new(500, 1)); // 4 / 8 * 100 = 50.0%
+        assert_eq!(average, Decimal::new(20, 1)); // 4 hours over 2 active days
+    }
+
+    #[test]
+    fn renders_skipped_intervals_and_summary_in_every_format() {
+        let mut factory = IntervalFactory::new();
+        factory.parse_projects(r#"["work"]"#).unwrap();
+
+        let interval = factory.new_interval(
+            r#"{"id":1,"start":"20241230T090000Z","end":"20241230T100000Z","tags":["work"]}"#,
+        ).unwrap().unwrap();
+        let skipped = SkippedInterval {
+            id: 2,
+            tags: vec!["other".to_string()],
+            reason: ErrorKind::IntervalWithNoProjects,
+        };
+
+        let report = Report::from_intervals(
+            &HashMap::new(),
+            &vec![interval],
+            Rounding::Exact,
+            RoundingStrategy::RoundHalfUp,
+            true,
+            vec![skipped],
+            Weekday::Mon,
+        );
+
+        let mut csv_output: Vec<u8> = Vec::new();
+        report.render(Format::Csv, &mut csv_output).unwrap();
+        let csv_text = std::str::from_utf8(&csv_output).unwrap();
+        assert!(csv_text.contains("percentage"));
+        assert!(csv_text.contains("IntervalWithNoProjects"));
+
+        let mut json_output: Vec<u8> = Vec::new();
+        report.render(Format::Json, &mut json_output).unwrap();
+        let json_text = std::str::from_utf8(&json_output).unwrap();
+        assert!(json_text.contains("\"avg_hours_per_day\""));
+        assert!(json_text.contains("\"skipped_intervals\""));
+        assert!(json_text.contains("IntervalWithNoProjects"));
+
+        let mut markdown_output: Vec<u8> = Vec::new();
+        report.render(Format::Markdown, &mut markdown_output).unwrap();
+        let markdown_text = std::str::from_utf8(&markdown_output).unwrap();
+        assert!(markdown_text.contains("| % | Avg |"));
+        assert!(markdown_text.contains("Skipped intervals"));
+        assert!(markdown_text.contains("IntervalWithNoProjects"));
+    }
 
     #[test]
     fn good_report() -> Result<(), io::Error> {
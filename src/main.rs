@@ -4,7 +4,12 @@ use std::io;
 use timewarrior_timesheet_report::{run, Config};
 
 fn main() -> Result<(), io::Error> {
-    match run(Config{}, &mut io::stdin().lock(), &mut io::stdout().lock()) {
+    let config = match Config::from_args(std::env::args()) {
+        Ok(config) => config,
+        Err(_) => panic!("Error parsing arguments"),
+    };
+
+    match run(config, &mut io::stdin().lock(), &mut io::stdout().lock()) {
         Ok(()) => Ok(()),
         Err(_) => panic!("Error in run"),
     }
@@ -14,7 +14,7 @@ fn check_input_buf(input: &mut dyn io::BufRead, expected: ExpectedValue) -> Resu
 }
 
 fn check_input_file(path: &std::path::Path) -> Result<(), TestError> {
-    let empty_config = report::Config{};
+    let empty_config = report::Config::default();
     let file = match fs::File::open(path) {
         Ok(file) => file,
         Err(error) => return Err(TestError(error.to_string())),